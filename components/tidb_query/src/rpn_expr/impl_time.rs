@@ -1,25 +1,254 @@
 // Copyright 2019 TiKV Project Authors. Licensed under Apache-2.0.
 
 use tidb_query_codegen::rpn_fn;
+use tipb::{Expr, ExprType};
 
 use super::super::expr::EvalContext;
 
 use crate::codec::data_type::*;
-use crate::codec::mysql::time::extension::{DateTimeExtension, MonthExtension};
+use crate::codec::mysql::time::extension::DateTimeExtension;
 use crate::codec::mysql::Time;
 use crate::codec::Error;
 use crate::expr::SqlMode;
 use crate::Result;
 
-#[rpn_fn(capture = [ctx])]
+/// A single piece of a compiled `DATE_FORMAT` layout. The byte string is parsed
+/// into a `Vec<FormatItem>` exactly once; each item is then rendered per row,
+/// which keeps the hot loop free of the repeated specifier scanning and
+/// re-dispatch that plain `Time::date_format` performs. This mirrors chrono's
+/// `StrftimeItems`/`format_with_items` split, where parsing the format is
+/// separated from applying it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatItem {
+    /// Literal bytes copied verbatim (runs of non-specifier input are merged).
+    Literal(Vec<u8>),
+    WeekdayNameAbbr,   // %a
+    MonthNameAbbr,     // %b
+    MonthNameLong,     // %M
+    MonthNumeric,      // %c
+    DayWithSuffix,     // %D
+    Day2,              // %d
+    DayNoPad,          // %e
+    MicroFrac,         // %f
+    Hour24,            // %H
+    Hour12,            // %h, %I
+    Minute2,           // %i
+    DayOfYear3,        // %j
+    Hour24NoPad,       // %k
+    Hour12NoPad,       // %l
+    Month2,            // %m
+    AmPm,              // %p
+    Time12,            // %r
+    Second2,           // %S, %s
+    Time24,            // %T
+    WeekMode0,         // %U
+    WeekMode1,         // %u
+    WeekMode2,         // %V
+    WeekMode3,         // %v
+    WeekdayNameLong,   // %W
+    WeekdayNumeric,    // %w
+    YearForWeekSunday, // %X
+    YearForWeekMonday, // %x
+    Year4,             // %Y
+    Year2,             // %y
+}
+
+/// Parses a `DATE_FORMAT` layout into a reusable item vector. Unknown
+/// specifiers and a trailing bare `%` fall through to their literal byte, which
+/// matches MySQL (`%z` renders as `z`).
+pub fn compile_format_items(layout: &[u8]) -> Vec<FormatItem> {
+    let mut items = Vec::new();
+    let mut literal = Vec::new();
+    let mut iter = layout.iter().cloned().peekable();
+    while let Some(b) = iter.next() {
+        if b != b'%' {
+            literal.push(b);
+            continue;
+        }
+        let spec = match iter.next() {
+            Some(spec) => spec,
+            None => {
+                literal.push(b'%');
+                break;
+            }
+        };
+        let item = match spec {
+            b'a' => FormatItem::WeekdayNameAbbr,
+            b'b' => FormatItem::MonthNameAbbr,
+            b'c' => FormatItem::MonthNumeric,
+            b'D' => FormatItem::DayWithSuffix,
+            b'd' => FormatItem::Day2,
+            b'e' => FormatItem::DayNoPad,
+            b'f' => FormatItem::MicroFrac,
+            b'H' => FormatItem::Hour24,
+            b'h' | b'I' => FormatItem::Hour12,
+            b'i' => FormatItem::Minute2,
+            b'j' => FormatItem::DayOfYear3,
+            b'k' => FormatItem::Hour24NoPad,
+            b'l' => FormatItem::Hour12NoPad,
+            b'M' => FormatItem::MonthNameLong,
+            b'm' => FormatItem::Month2,
+            b'p' => FormatItem::AmPm,
+            b'r' => FormatItem::Time12,
+            b'S' | b's' => FormatItem::Second2,
+            b'T' => FormatItem::Time24,
+            b'U' => FormatItem::WeekMode0,
+            b'u' => FormatItem::WeekMode1,
+            b'V' => FormatItem::WeekMode2,
+            b'v' => FormatItem::WeekMode3,
+            b'W' => FormatItem::WeekdayNameLong,
+            b'w' => FormatItem::WeekdayNumeric,
+            b'X' => FormatItem::YearForWeekSunday,
+            b'x' => FormatItem::YearForWeekMonday,
+            b'Y' => FormatItem::Year4,
+            b'y' => FormatItem::Year2,
+            b'%' => {
+                literal.push(b'%');
+                continue;
+            }
+            other => {
+                literal.push(other);
+                continue;
+            }
+        };
+        if !literal.is_empty() {
+            items.push(FormatItem::Literal(std::mem::replace(&mut literal, Vec::new())));
+        }
+        items.push(item);
+    }
+    if !literal.is_empty() {
+        items.push(FormatItem::Literal(literal));
+    }
+    items
+}
+
+/// English ordinal suffix for the day-of-month used by `%D`.
+fn day_suffix(day: u32) -> &'static str {
+    if (11..=13).contains(&(day % 100)) {
+        "th"
+    } else {
+        match day % 10 {
+            1 => "st",
+            2 => "nd",
+            3 => "rd",
+            _ => "th",
+        }
+    }
+}
+
+/// Applies a precompiled layout to `t`, looking month/weekday/AM-PM names up in
+/// the active locale's [`TimeNames`].
+///
+/// This item-based renderer is the single canonical `DATE_FORMAT` engine for
+/// the rpn layer: both the constant-layout path (precompiled once in
+/// [`init_date_format_metadata`]) and the per-row path in [`date_format`] go
+/// through it, so they cannot diverge on specifier edge cases. It supersedes
+/// `DateTimeExtension::date_format`, which only emitted English names and could
+/// not be precompiled; to keep the replacement honest, `test_date_format` pins
+/// its output to the exact bytes the old engine produced for every specifier
+/// it exercises (all but `%I`, `%l` and `%S`), and
+/// `test_date_format_week_agreement` cross-checks the week specifiers
+/// against `WEEK`/`YEARWEEK`.
+fn apply_format_items(ctx: &EvalContext, t: &DateTime, items: &[FormatItem]) -> Bytes {
+    use std::io::Write;
+
+    let names = time_names(ctx);
+    let hour = t.hour();
+    let (ampm_idx, hour12) = if hour < 12 {
+        (0, if hour == 0 { 12 } else { hour })
+    } else {
+        (1, if hour == 12 { 12 } else { hour - 12 })
+    };
+    let weekday_mon = t.weekday().num_days_from_monday() as usize;
+
+    let mut buf = Bytes::new();
+    // `buf` is a `Vec<u8>`, so numeric specifiers format straight into it with
+    // `write!` and name/AM-PM specifiers push their static `&str` slice — no
+    // throwaway `String` is allocated per specifier per row.
+    for item in items {
+        match item {
+            FormatItem::Literal(bytes) => buf.extend_from_slice(bytes),
+            FormatItem::WeekdayNameAbbr => {
+                buf.extend_from_slice(names.weekdays_abbr[weekday_mon].as_bytes())
+            }
+            FormatItem::MonthNameAbbr => {
+                buf.extend_from_slice(names.months_abbr[(t.month() - 1) as usize].as_bytes())
+            }
+            FormatItem::MonthNameLong => {
+                buf.extend_from_slice(names.months[(t.month() - 1) as usize].as_bytes())
+            }
+            FormatItem::MonthNumeric => write!(buf, "{}", t.month()).unwrap(),
+            FormatItem::DayWithSuffix => write!(buf, "{}{}", t.day(), day_suffix(t.day())).unwrap(),
+            FormatItem::Day2 => write!(buf, "{:02}", t.day()).unwrap(),
+            FormatItem::DayNoPad => write!(buf, "{}", t.day()).unwrap(),
+            FormatItem::MicroFrac => write!(buf, "{:06}", t.micro()).unwrap(),
+            FormatItem::Hour24 => write!(buf, "{:02}", hour).unwrap(),
+            FormatItem::Hour12 => write!(buf, "{:02}", hour12).unwrap(),
+            FormatItem::Minute2 => write!(buf, "{:02}", t.minute()).unwrap(),
+            FormatItem::DayOfYear3 => write!(buf, "{:03}", t.days()).unwrap(),
+            FormatItem::Hour24NoPad => write!(buf, "{}", hour).unwrap(),
+            FormatItem::Hour12NoPad => write!(buf, "{}", hour12).unwrap(),
+            FormatItem::Month2 => write!(buf, "{:02}", t.month()).unwrap(),
+            FormatItem::AmPm => buf.extend_from_slice(names.ampm[ampm_idx].as_bytes()),
+            FormatItem::Time12 => write!(
+                buf,
+                "{:02}:{:02}:{:02} {}",
+                hour12,
+                t.minute(),
+                t.second(),
+                names.ampm[ampm_idx]
+            )
+            .unwrap(),
+            FormatItem::Second2 => write!(buf, "{:02}", t.second()).unwrap(),
+            FormatItem::Time24 => {
+                write!(buf, "{:02}:{:02}:{:02}", hour, t.minute(), t.second()).unwrap()
+            }
+            FormatItem::WeekMode0 => write!(buf, "{:02}", calc_week(t, week_mode(0)).0).unwrap(),
+            FormatItem::WeekMode1 => write!(buf, "{:02}", calc_week(t, week_mode(1)).0).unwrap(),
+            FormatItem::WeekMode2 => write!(buf, "{:02}", calc_week(t, week_mode(2)).0).unwrap(),
+            FormatItem::WeekMode3 => write!(buf, "{:02}", calc_week(t, week_mode(3)).0).unwrap(),
+            FormatItem::WeekdayNameLong => {
+                buf.extend_from_slice(names.weekdays[weekday_mon].as_bytes())
+            }
+            FormatItem::WeekdayNumeric => {
+                write!(buf, "{}", t.weekday().num_days_from_sunday()).unwrap()
+            }
+            FormatItem::YearForWeekSunday => {
+                write!(buf, "{:04}", calc_week(t, week_mode(2)).1 as u32).unwrap()
+            }
+            FormatItem::YearForWeekMonday => {
+                write!(buf, "{:04}", calc_week(t, week_mode(3)).1 as u32).unwrap()
+            }
+            FormatItem::Year4 => write!(buf, "{:04}", t.year()).unwrap(),
+            FormatItem::Year2 => write!(buf, "{:02}", t.year() % 100).unwrap(),
+        }
+    }
+    buf
+}
+
+/// Builds the `date_format` metadata: when the `layout` operand is a constant
+/// node we compile its item vector once here, so the per-row path can reuse it
+/// instead of rescanning the byte string for every row.
+fn init_date_format_metadata(expr: &mut Expr) -> Result<Option<Vec<FormatItem>>> {
+    let children = expr.get_children();
+    if children.len() != 2 {
+        return Ok(None);
+    }
+    let layout = &children[1];
+    match layout.get_tp() {
+        ExprType::Bytes | ExprType::String => Ok(Some(compile_format_items(layout.get_val()))),
+        _ => Ok(None),
+    }
+}
+
+#[rpn_fn(capture = [ctx, metadata], metadata_type = Option<Vec<FormatItem>>, metadata_mapper = init_date_format_metadata)]
 #[inline]
 pub fn date_format(
     ctx: &mut EvalContext,
+    metadata: &Option<Vec<FormatItem>>,
     t: &Option<DateTime>,
     layout: &Option<Bytes>,
 ) -> Result<Option<Bytes>> {
-    use std::str::from_utf8;
-
     if t.is_none() || layout.is_none() {
         return Ok(None);
     }
@@ -30,12 +259,18 @@ pub fn date_format(
             .map(|_| Ok(None))?;
     }
 
-    let t = t.date_format(from_utf8(layout.as_slice()).map_err(Error::Encoding)?);
-    if let Err(err) = t {
-        return ctx.handle_invalid_time_error(err).map(|_| Ok(None))?;
-    }
+    // Reuse the compiled items for a constant layout; otherwise compile the
+    // per-row layout once before applying it.
+    let compiled;
+    let items = match metadata {
+        Some(items) => items.as_slice(),
+        None => {
+            compiled = compile_format_items(layout.as_slice());
+            compiled.as_slice()
+        }
+    };
 
-    Ok(Some(t.unwrap().into_bytes()))
+    Ok(Some(apply_format_items(ctx, t, items)))
 }
 
 #[rpn_fn(capture = [ctx])]
@@ -54,6 +289,201 @@ pub fn week_day(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<In
     Ok(Some(i64::from(day)))
 }
 
+// Mode flags shared by MySQL's WEEK()/YEARWEEK() family, mirroring the server's
+// `WEEK_MONDAY_FIRST` / `WEEK_YEAR` / `WEEK_FIRST_WEEKDAY` bits.
+const WEEK_MONDAY_FIRST: u32 = 1;
+const WEEK_YEAR: u32 = 2;
+const WEEK_FIRST_WEEKDAY: u32 = 4;
+
+/// Normalizes a user supplied mode the way MySQL's `week_mode` does: only the
+/// low three bits matter, and when the week does not start on Monday the
+/// `WEEK_FIRST_WEEKDAY` flag is toggled. In [`calc_week`], with that flag set
+/// week 1 is the first week containing the first day of the week; with it
+/// clear, week 1 is the first week that has 4 or more days in the year.
+fn week_mode(mode: u32) -> u32 {
+    let mut week_format = mode & 7;
+    if week_format & WEEK_MONDAY_FIRST == 0 {
+        week_format ^= WEEK_FIRST_WEEKDAY;
+    }
+    week_format
+}
+
+fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_year(year: i32) -> i32 {
+    if is_leap_year(year) {
+        366
+    } else {
+        365
+    }
+}
+
+/// Number of days since year 0, matching MySQL's `calc_daynr`.
+fn calc_day_number(year: i32, month: i32, day: i32) -> i32 {
+    if year == 0 && month == 0 {
+        return 0;
+    }
+    let mut delsum = 365 * year + 31 * (month - 1) + day;
+    let mut y = year;
+    if month <= 2 {
+        y -= 1;
+    } else {
+        delsum -= (month * 4 + 23) / 10;
+    }
+    delsum + y / 4 - ((y / 100 + 1) * 3) / 4
+}
+
+/// Weekday of a day number, counted so that Sunday is 0 when `sunday_first`,
+/// otherwise Monday is 0 (MySQL's `calc_weekday`).
+fn calc_weekday(daynr: i32, sunday_first: bool) -> i32 {
+    (daynr + 5 + if sunday_first { 1 } else { 0 }) % 7
+}
+
+/// MySQL's `calc_week`: returns the `(week, year)` pair for `t` under `mode`.
+/// The caller is expected to have already normalized `mode` with [`week_mode`].
+fn calc_week(t: &DateTime, mode: u32) -> (i32, i32) {
+    let mut year = t.year() as i32;
+    let month = t.month() as i32;
+    let day = t.day() as i32;
+
+    let daynr = calc_day_number(year, month, day);
+    let mut first_daynr = calc_day_number(year, 1, 1);
+
+    let monday_first = mode & WEEK_MONDAY_FIRST != 0;
+    let mut week_year = mode & WEEK_YEAR != 0;
+    let first_weekday = mode & WEEK_FIRST_WEEKDAY != 0;
+
+    let mut weekday = calc_weekday(first_daynr, !monday_first);
+
+    if month == 1 && day <= 7 - weekday {
+        if !week_year
+            && ((first_weekday && weekday != 0) || (!first_weekday && weekday >= 4))
+        {
+            return (0, year);
+        }
+        week_year = true;
+        year -= 1;
+        let diy = days_in_year(year);
+        first_daynr -= diy;
+        weekday = (weekday + 53 * 7 - diy) % 7;
+    }
+
+    let mut days = if (first_weekday && weekday != 0) || (!first_weekday && weekday >= 4) {
+        daynr - (first_daynr + (7 - weekday))
+    } else {
+        daynr - (first_daynr - weekday)
+    };
+
+    if week_year && days >= 52 * 7 {
+        weekday = (weekday + days_in_year(year)) % 7;
+        if (!first_weekday && weekday < 4) || (first_weekday && weekday == 0) {
+            return (1, year + 1);
+        }
+    }
+    (days / 7 + 1, year)
+}
+
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn week_with_mode(
+    ctx: &mut EvalContext,
+    t: &Option<DateTime>,
+    mode: &Option<Int>,
+) -> Result<Option<Int>> {
+    if t.is_none() || mode.is_none() {
+        return Ok(None);
+    }
+    let t = t.as_ref().unwrap();
+    if t.invalid_zero() {
+        return ctx
+            .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+            .map(|_| Ok(None))?;
+    }
+    let mode = *mode.as_ref().unwrap();
+    let (week, _) = calc_week(t, week_mode(mode as u32));
+    Ok(Some(Int::from(week)))
+}
+
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn week(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Int>> {
+    if t.is_none() {
+        return Ok(None);
+    }
+    let t = t.as_ref().unwrap();
+    if t.invalid_zero() {
+        return ctx
+            .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+            .map(|_| Ok(None))?;
+    }
+    let (week, _) = calc_week(t, week_mode(0));
+    Ok(Some(Int::from(week)))
+}
+
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn year_week_with_mode(
+    ctx: &mut EvalContext,
+    t: &Option<DateTime>,
+    mode: &Option<Int>,
+) -> Result<Option<Int>> {
+    if t.is_none() || mode.is_none() {
+        return Ok(None);
+    }
+    let t = t.as_ref().unwrap();
+    if t.invalid_zero() {
+        return ctx
+            .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+            .map(|_| Ok(None))?;
+    }
+    let mode = *mode.as_ref().unwrap();
+    let (week, year) = calc_week(t, week_mode(mode as u32) | WEEK_YEAR);
+    let mut result = i64::from(week) + i64::from(year) * 100;
+    if result < 0 {
+        result = i64::from(u32::max_value());
+    }
+    Ok(Some(result))
+}
+
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn year_week(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Int>> {
+    if t.is_none() {
+        return Ok(None);
+    }
+    let t = t.as_ref().unwrap();
+    if t.invalid_zero() {
+        return ctx
+            .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+            .map(|_| Ok(None))?;
+    }
+    let (week, year) = calc_week(t, week_mode(0) | WEEK_YEAR);
+    let mut result = i64::from(week) + i64::from(year) * 100;
+    if result < 0 {
+        result = i64::from(u32::max_value());
+    }
+    Ok(Some(result))
+}
+
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn week_of_year(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Int>> {
+    if t.is_none() {
+        return Ok(None);
+    }
+    let t = t.as_ref().unwrap();
+    if t.invalid_zero() {
+        return ctx
+            .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+            .map(|_| Ok(None))?;
+    }
+    // WEEKOFYEAR(date) is the ISO-like WEEK(date, 3).
+    let (week, _) = calc_week(t, week_mode(3));
+    Ok(Some(Int::from(week)))
+}
+
 #[rpn_fn(capture = [ctx])]
 #[inline]
 pub fn day_of_year(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Int>> {
@@ -79,6 +509,49 @@ pub fn from_days(ctx: &mut EvalContext, arg: &Option<Int>) -> Result<Option<Time
     })
 }
 
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn to_days(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Int>> {
+    let t = match t {
+        Some(v) => v,
+        _ => return Ok(None),
+    };
+    if t.is_zero() {
+        if ctx.cfg.sql_mode.contains(SqlMode::NO_ZERO_DATE) {
+            return ctx
+                .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+                .map(|_| Ok(None))?;
+        }
+        return Ok(None);
+    }
+    // Same day-number basis as `Time::from_days`, so `from_days(to_days(d)) == d`.
+    let daynr = calc_day_number(t.year() as i32, t.month() as i32, t.day() as i32);
+    Ok(Some(Int::from(daynr)))
+}
+
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn to_seconds(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Int>> {
+    let t = match t {
+        Some(v) => v,
+        _ => return Ok(None),
+    };
+    if t.is_zero() {
+        if ctx.cfg.sql_mode.contains(SqlMode::NO_ZERO_DATE) {
+            return ctx
+                .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+                .map(|_| Ok(None))?;
+        }
+        return Ok(None);
+    }
+    let daynr = calc_day_number(t.year() as i32, t.month() as i32, t.day() as i32);
+    let secs = i64::from(daynr) * 86400
+        + i64::from(t.hour()) * 3600
+        + i64::from(t.minute()) * 60
+        + i64::from(t.second());
+    Ok(Some(secs))
+}
+
 #[rpn_fn]
 #[inline]
 pub fn month(t: &Option<DateTime>) -> Result<Option<Int>> {
@@ -147,6 +620,147 @@ pub fn day_of_month(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Optio
     Ok(Some(Int::from(t.day())))
 }
 
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn quarter(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Int>> {
+    let t = match t {
+        Some(v) => v,
+        _ => return Ok(None),
+    };
+
+    if t.is_zero() {
+        if ctx.cfg.sql_mode.contains(SqlMode::NO_ZERO_DATE) {
+            return ctx
+                .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+                .map(|_| Ok(None))?;
+        }
+        return Ok(Some(0));
+    }
+    Ok(Some(Int::from((t.month() + 2) / 3)))
+}
+
+/// Days in `month` of `year`, accounting for February in leap years.
+fn last_day_of_month(year: u32, month: u32) -> u32 {
+    const DAYS_IN_MONTH: [u32; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    if month == 2 {
+        if is_leap_year(year as i32) {
+            29
+        } else {
+            28
+        }
+    } else {
+        DAYS_IN_MONTH[(month - 1) as usize]
+    }
+}
+
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn last_day(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Time>> {
+    let t = match t {
+        Some(v) => v,
+        _ => return Ok(None),
+    };
+
+    if t.is_zero() {
+        if ctx.cfg.sql_mode.contains(SqlMode::NO_ZERO_DATE) {
+            return ctx
+                .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+                .map(|_| Ok(None))?;
+        }
+        return Ok(None);
+    }
+    if t.month() == 0 {
+        return Ok(None);
+    }
+    let day = last_day_of_month(t.year(), t.month());
+    // Rebuild a date-only value so the time part is zeroed, mirroring
+    // `from_days` which also yields a plain date.
+    let date = format!("{:04}-{:02}-{:02}", t.year(), t.month(), day);
+    Ok(Some(Time::parse_date(ctx, &date)?))
+}
+
+/// Per-locale month and weekday names used by `DATE_FORMAT`, `MONTHNAME` and
+/// `DAYNAME`, mirroring MySQL's `lc_time_names`. The format items stay the same
+/// across locales; only the name arrays selected here differ.
+pub struct TimeNames {
+    pub months: [&'static str; 12],
+    pub months_abbr: [&'static str; 12],
+    /// Weekday names, Monday first.
+    pub weekdays: [&'static str; 7],
+    pub weekdays_abbr: [&'static str; 7],
+    pub ampm: [&'static str; 2],
+}
+
+static EN_US: TimeNames = TimeNames {
+    months: [
+        "January", "February", "March", "April", "May", "June", "July", "August", "September",
+        "October", "November", "December",
+    ],
+    months_abbr: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    weekdays: [
+        "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday",
+    ],
+    weekdays_abbr: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    ampm: ["AM", "PM"],
+};
+
+static FR_FR: TimeNames = TimeNames {
+    months: [
+        "janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre",
+        "octobre", "novembre", "décembre",
+    ],
+    months_abbr: [
+        "jan", "fév", "mar", "avr", "mai", "jui", "jul", "aoû", "sep", "oct", "nov", "déc",
+    ],
+    weekdays: [
+        "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+    ],
+    weekdays_abbr: ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"],
+    ampm: ["AM", "PM"],
+};
+
+static JA_JP: TimeNames = TimeNames {
+    months: [
+        "1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月",
+    ],
+    months_abbr: [
+        "1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月",
+    ],
+    weekdays: [
+        "月曜日", "火曜日", "水曜日", "木曜日", "金曜日", "土曜日", "日曜日",
+    ],
+    weekdays_abbr: ["月", "火", "水", "木", "金", "土", "日"],
+    ampm: ["午前", "午後"],
+};
+
+static DE_DE: TimeNames = TimeNames {
+    months: [
+        "Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September",
+        "Oktober", "November", "Dezember",
+    ],
+    months_abbr: [
+        "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+    weekdays: [
+        "Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag",
+    ],
+    weekdays_abbr: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+    ampm: ["AM", "PM"],
+};
+
+/// Resolves the active session locale (`lc_time_names`) to its name tables,
+/// falling back to `en_US` for any locale we don't ship.
+pub fn time_names(ctx: &EvalContext) -> &'static TimeNames {
+    match ctx.cfg.lc_time_names.as_str() {
+        "fr_FR" => &FR_FR,
+        "ja_JP" => &JA_JP,
+        "de_DE" => &DE_DE,
+        _ => &EN_US,
+    }
+}
+
 #[rpn_fn(capture = [ctx])]
 #[inline]
 pub fn month_name(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Bytes>> {
@@ -155,14 +769,34 @@ pub fn month_name(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<
             if t.is_zero() && ctx.cfg.sql_mode.contains(SqlMode::NO_ZERO_DATE) {
                 ctx.handle_invalid_time_error(Error::incorrect_datetime_value(t))
                     .map(|_| Ok(None))?
+            } else if t.month() == 0 {
+                Ok(None)
             } else {
-                Ok(t.month_name().map(|s| s.to_string().into_bytes()))
+                let name = time_names(ctx).months[(t.month() - 1) as usize];
+                Ok(Some(name.as_bytes().to_vec()))
             }
         }
         None => Ok(None),
     }
 }
 
+#[rpn_fn(capture = [ctx])]
+#[inline]
+pub fn day_name(ctx: &mut EvalContext, t: &Option<DateTime>) -> Result<Option<Bytes>> {
+    if t.is_none() {
+        return Ok(None);
+    }
+    let t = t.as_ref().unwrap();
+    if t.invalid_zero() {
+        return ctx
+            .handle_invalid_time_error(Error::incorrect_datetime_value(&format!("{}", t)))
+            .map(|_| Ok(None))?;
+    }
+    let idx = t.weekday().num_days_from_monday() as usize;
+    let name = time_names(ctx).weekdays[idx];
+    Ok(Some(name.as_bytes().to_vec()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,6 +930,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compile_format_items() {
+        use FormatItem::*;
+
+        assert_eq!(
+            compile_format_items(b"%Y-%m-%d"),
+            vec![Year4, Literal(b"-".to_vec()), Month2, Literal(b"-".to_vec()), Day2]
+        );
+        // Literal runs are merged, `%%` collapses to a literal `%`, and an
+        // unknown specifier keeps only its trailing byte (like MySQL's `%z`).
+        assert_eq!(
+            compile_format_items(b"abc%%%z!"),
+            vec![Literal(b"abc%z!".to_vec())]
+        );
+        // A trailing bare `%` renders as a literal `%`.
+        assert_eq!(compile_format_items(b"%"), vec![Literal(b"%".to_vec())]);
+    }
+
     #[test]
     fn test_week_day() {
         let cases = vec![
@@ -354,6 +1006,154 @@ mod tests {
         assert_eq!(output, None);
     }
 
+    #[test]
+    fn test_week_with_mode() {
+        let cases = vec![
+            ("2008-02-20 00:00:00", 1, Some(8i64)),
+            ("2008-12-31 00:00:00", 1, Some(53i64)),
+            ("2000-01-01 00:00:00", 0, Some(0i64)),
+            ("2000-01-01 00:00:00", 2, Some(52i64)),
+        ];
+        let mut ctx = EvalContext::default();
+        for (arg, mode, exp) in cases {
+            let datetime = Some(DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap());
+            let output = RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .push_param(Some(mode as i64))
+                .evaluate(ScalarFuncSig::WeekWithMode)
+                .unwrap();
+            assert_eq!(output, exp);
+        }
+        let output = RpnFnScalarEvaluator::new()
+            .push_param(None::<DateTime>)
+            .push_param(Some(0i64))
+            .evaluate::<Int>(ScalarFuncSig::WeekWithMode)
+            .unwrap();
+        assert_eq!(output, None);
+    }
+
+    #[test]
+    fn test_week() {
+        let cases = vec![
+            ("2008-02-20 00:00:00", Some(7i64)),
+            ("2000-01-01 00:00:00", Some(0i64)),
+            ("0000-00-00", None),
+            ("2018-12-00", None),
+        ];
+        let mut ctx = EvalContext::default();
+        for (arg, exp) in cases {
+            let datetime = Some(DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap());
+            let output = RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .evaluate(ScalarFuncSig::WeekWithoutMode)
+                .unwrap();
+            assert_eq!(output, exp);
+        }
+    }
+
+    #[test]
+    fn test_year_week_with_mode() {
+        let cases = vec![
+            ("1987-01-01 00:00:00", 0, Some(198652i64)),
+            ("2000-01-01 00:00:00", 0, Some(199952i64)),
+            ("2008-02-20 00:00:00", 1, Some(200808i64)),
+        ];
+        let mut ctx = EvalContext::default();
+        for (arg, mode, exp) in cases {
+            let datetime = Some(DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap());
+            let output = RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .push_param(Some(mode as i64))
+                .evaluate(ScalarFuncSig::YearWeekWithMode)
+                .unwrap();
+            assert_eq!(output, exp);
+        }
+    }
+
+    #[test]
+    fn test_year_week() {
+        let cases = vec![
+            ("1987-01-01 00:00:00", Some(198652i64)),
+            ("2008-02-20 00:00:00", Some(200807i64)),
+            ("0000-00-00", None),
+        ];
+        let mut ctx = EvalContext::default();
+        for (arg, exp) in cases {
+            let datetime = Some(DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap());
+            let output = RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .evaluate(ScalarFuncSig::YearWeekWithoutMode)
+                .unwrap();
+            assert_eq!(output, exp);
+        }
+    }
+
+    #[test]
+    fn test_week_of_year() {
+        let cases = vec![
+            ("2008-02-20 00:00:00", Some(8i64)),
+            ("2008-12-31 00:00:00", Some(1i64)),
+            ("1987-01-01 00:00:00", Some(1i64)),
+            ("0000-00-00", None),
+        ];
+        let mut ctx = EvalContext::default();
+        for (arg, exp) in cases {
+            let datetime = Some(DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap());
+            let output = RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .evaluate(ScalarFuncSig::WeekOfYear)
+                .unwrap();
+            assert_eq!(output, exp);
+        }
+    }
+
+    #[test]
+    fn test_date_format_week_agreement() {
+        // The %U/%u/%V/%v/%X/%x specifiers route through the same calc_week
+        // computation as WEEK()/YEARWEEK(), so formatting and the functions must
+        // agree even on the 0000-01-01 edge case the old single-mode week() got
+        // wrong.
+        let mut ctx = EvalContext::default();
+        let format_spec = |datetime: &Option<DateTime>, spec: &str| {
+            RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .push_param(Some(spec.as_bytes().to_vec()))
+                .evaluate::<Bytes>(ScalarFuncSig::DateFormatSig)
+                .unwrap()
+                .unwrap()
+        };
+
+        for arg in &["2010-01-07 23:12:34.12345", "0000-01-01 00:00:00.123456"] {
+            let datetime = Some(DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap());
+            // %U/%u/%V/%v are WEEK() under modes 0..=3.
+            for (spec, mode) in &[("%U", 0i64), ("%u", 1), ("%V", 2), ("%v", 3)] {
+                let formatted = format_spec(&datetime, spec);
+                let week = RpnFnScalarEvaluator::new()
+                    .push_param(datetime.clone())
+                    .push_param(Some(*mode))
+                    .evaluate::<Int>(ScalarFuncSig::WeekWithMode)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(formatted, format!("{:02}", week).into_bytes(), "{} {}", arg, spec);
+            }
+        }
+
+        // %X/%x carry the week-year of YEARWEEK() under modes 2 and 3.
+        let datetime =
+            Some(DateTime::parse_datetime(&mut ctx, "2010-01-07 23:12:34.12345", 6, true).unwrap());
+        for (spec, mode) in &[("%X", 2i64), ("%x", 3)] {
+            let formatted = format_spec(&datetime, spec);
+            let year_week = RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .push_param(Some(*mode))
+                .evaluate::<Int>(ScalarFuncSig::YearWeekWithMode)
+                .unwrap()
+                .unwrap();
+            let year = (year_week / 100) as u32;
+            assert_eq!(formatted, format!("{:04}", year).into_bytes(), "{} {}", spec, year_week);
+        }
+    }
+
     #[test]
     fn test_from_days() {
         let cases = vec![
@@ -386,6 +1186,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_to_days() {
+        // `from_days(to_days(d)) == d` for the `test_from_days` date fixtures.
+        let cases = vec![
+            (735_000i64, "2012-05-12"),
+            (735_030, "2012-06-11"),
+            (735_130, "2012-09-19"),
+            (734_909, "2012-02-11"),
+            (734_878, "2012-01-11"),
+            (734_927, "2012-02-29"),
+            (734_634, "2011-05-12"),
+            (734_664, "2011-06-11"),
+            (734_764, "2011-09-19"),
+            (734_544, "2011-02-11"),
+            (734_513, "2011-01-11"),
+            (3_652_424, "9999-12-31"),
+        ];
+        let mut ctx = EvalContext::default();
+        for (exp, date) in cases {
+            let datetime = Some(Time::parse_date(&mut ctx, date).unwrap());
+            let output = RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .evaluate(ScalarFuncSig::ToDays)
+                .unwrap();
+            assert_eq!(output, Some(exp));
+
+            // Round-trip back through FROM_DAYS.
+            let roundtrip: Option<Time> = RpnFnScalarEvaluator::new()
+                .push_param(Some(exp))
+                .evaluate(ScalarFuncSig::FromDays)
+                .unwrap();
+            assert_eq!(roundtrip, datetime);
+        }
+
+        let output = RpnFnScalarEvaluator::new()
+            .push_param(None::<DateTime>)
+            .evaluate::<Int>(ScalarFuncSig::ToDays)
+            .unwrap();
+        assert_eq!(output, None);
+    }
+
+    #[test]
+    fn test_to_seconds() {
+        let cases = vec![
+            ("2012-05-12 00:00:00", Some(735_000i64 * 86400)),
+            ("1970-01-01 00:00:00", Some(719_528i64 * 86400)),
+            ("2012-05-12 01:02:03", Some(735_000i64 * 86400 + 3723)),
+            ("0000-00-00 00:00:00", None),
+        ];
+        let mut ctx = EvalContext::default();
+        for (arg, exp) in cases {
+            let datetime = Some(DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap());
+            let output = RpnFnScalarEvaluator::new()
+                .push_param(datetime.clone())
+                .evaluate(ScalarFuncSig::ToSeconds)
+                .unwrap();
+            assert_eq!(output, exp);
+        }
+    }
+
     #[test]
     fn test_month() {
         let cases = vec![
@@ -516,6 +1376,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_quarter() {
+        let cases = vec![
+            (Some("0000-00-00 00:00:00"), Some(0i64)),
+            (Some("2018-01-01 01:01:01"), Some(1i64)),
+            (Some("2018-03-01 01:01:01"), Some(1i64)),
+            (Some("2018-04-01 01:01:01"), Some(2i64)),
+            (Some("2018-06-01 01:01:01"), Some(2i64)),
+            (Some("2018-07-01 01:01:01"), Some(3i64)),
+            (Some("2018-09-01 01:01:01"), Some(3i64)),
+            (Some("2018-10-01 01:01:01"), Some(4i64)),
+            (Some("2018-12-01 01:01:01"), Some(4i64)),
+            (None, None),
+        ];
+        let mut ctx = EvalContext::default();
+        for (time, expect) in cases {
+            let time = time.map(|t| DateTime::parse_datetime(&mut ctx, t, 6, true).unwrap());
+            let output = RpnFnScalarEvaluator::new()
+                .push_param(time)
+                .evaluate(ScalarFuncSig::Quarter)
+                .unwrap();
+            assert_eq!(output, expect);
+        }
+    }
+
+    #[test]
+    fn test_last_day() {
+        let cases = vec![
+            ("2018-02-15 00:00:00", Some("2018-02-28")),
+            ("2016-02-15 00:00:00", Some("2016-02-29")),
+            ("2018-04-10 12:34:56", Some("2018-04-30")),
+            ("2018-01-01 00:00:00", Some("2018-01-31")),
+            ("2018-12-31 00:00:00", Some("2018-12-31")),
+            ("0000-00-00 00:00:00", None),
+        ];
+        let mut ctx = EvalContext::default();
+        for (arg, exp) in cases {
+            let datetime = Some(DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap());
+            let exp = exp.map(|e| Time::parse_date(&mut ctx, e).unwrap());
+            let output: Option<Time> = RpnFnScalarEvaluator::new()
+                .push_param(datetime)
+                .evaluate(ScalarFuncSig::LastDay)
+                .unwrap();
+            assert_eq!(output, exp);
+        }
+        let output = RpnFnScalarEvaluator::new()
+            .push_param(None::<DateTime>)
+            .evaluate::<Time>(ScalarFuncSig::LastDay)
+            .unwrap();
+        assert_eq!(output, None);
+    }
+
     #[test]
     fn test_month_name() {
         let cases = vec![
@@ -562,4 +1474,32 @@ mod tests {
         );
         assert!(output.unwrap().is_none());
     }
+
+    #[test]
+    fn test_day_name() {
+        let cases = vec![
+            (None, None),
+            (Some("2019-11-25 00:00:00.000000"), Some("Monday")),
+            (Some("2019-11-26 00:00:00.000000"), Some("Tuesday")),
+            (Some("2019-11-27 00:00:00.000000"), Some("Wednesday")),
+            (Some("2019-11-28 00:00:00.000000"), Some("Thursday")),
+            (Some("2019-11-29 00:00:00.000000"), Some("Friday")),
+            (Some("2019-11-30 00:00:00.000000"), Some("Saturday")),
+            (Some("2019-12-01 00:00:00.000000"), Some("Sunday")),
+            (Some("0000-00-00 00:00:00.000000"), None),
+            (Some("2019-00-01 00:00:00.000000"), None),
+        ];
+        let mut ctx = EvalContext::default();
+        for (arg, exp) in cases {
+            let arg = arg.map(|arg: &str| {
+                DateTime::parse_datetime(&mut ctx, arg, 6, true).unwrap()
+            });
+            let output: Option<Bytes> = RpnFnScalarEvaluator::new()
+                .push_param(arg)
+                .evaluate(ScalarFuncSig::DayName)
+                .unwrap();
+            let exp = exp.map(|v| v.as_bytes().to_vec());
+            assert_eq!(output, exp);
+        }
+    }
 }